@@ -80,6 +80,39 @@ pub fn args() -> App<'static, 'static> {
                         .long("distances"),
                 ),
         )
+        // Like the other subcommands in this module, dispatch (path exploration + writing
+        // one script per path via `solver::external::ExternalSolver`) lives in the binary
+        // entrypoint, not here; this only defines the `smt` subcommand's argument surface.
+        .subcommand(
+            App::new("smt")
+                .about("Export path condition constraints of a RISC-U ELF binary as SMT-LIB2")
+                .arg(
+                    Arg::with_name("input-file")
+                        .help("Source RISC-U binary to be analyzed")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("output-file")
+                        .help("Output file (or directory, one script per path) to write to")
+                        .short("o")
+                        .long("output-file")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .default_value("smt-out"),
+                )
+                .arg(
+                    Arg::with_name("max-execution-depth")
+                        .help("Number of instructions, where the path execution will be aborted")
+                        .short("d")
+                        .long("execution-depth")
+                        .takes_value(true)
+                        .value_name("NUMBER")
+                        .default_value(formatcp!("{}", symbolic_defaults::MAX_EXECUTION_DEPTH))
+                        .validator(is::<u64>),
+                ),
+        )
         .subcommand(
             App::new("execute")
                 .about("Symbolically execute a RISC-U ELF binary")
@@ -281,6 +314,17 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_smt_defaults_are_set() {
+        with_matches(vec!["monster", "smt", "file.o"], |m| {
+            assert!(m.is_present("output-file"), "Default output file is set");
+            assert!(
+                m.is_present("max-execution-depth"),
+                "Default execution depth is set"
+            );
+        });
+    }
+
     #[test]
     fn test_execute_memory_size_argument() {
         assert!(
@@ -3,16 +3,43 @@ use super::{
 };
 use std::{
     collections::HashMap,
+    ffi::OsStr,
     fs::File,
-    io::{stdout, BufWriter, Write},
+    io::{stdout, BufRead, BufReader, BufWriter, ErrorKind, Read, Write},
     path::Path,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
     sync::{Arc, Mutex},
 };
 
 pub struct ExternalSolver {
     output: Arc<Mutex<dyn Write + Send>>,
+    response: Option<Arc<Mutex<BufReader<Box<dyn Read + Send>>>>>,
+    // handle of the spawned solver process; its stdin and stdout have been
+    // taken out of it and live on in `output` and `response`
+    _process: Option<Child>,
+    // Nodes already declared/asserted in an earlier `solve_impl` call, kept
+    // around so a later query sharing the same subformula does not re-emit
+    // them. Sound only as long as `SymbolId`s are never reused for a
+    // different node across the lifetime of this solver: `solve_impl` relies
+    // on that numbering being globally stable to know a cached id still
+    // refers to the same declaration.
+    visited: Mutex<HashMap<SymbolId, Result<SymbolId, SolverError>>>,
+    // Sort (Bool vs BitVec) of every node declared so far. Must persist
+    // alongside `visited`: once a node is skipped by `traverse` because it is
+    // already cached, its sort is never reported again by `SmtPrinter`, so a
+    // later query referencing it still needs to look the sort up here to
+    // type-check the term it builds around that node.
+    sorts: Mutex<HashMap<SymbolId, SmtSort>>,
+    // Every input symbol declared so far. Must persist for the same reason
+    // `sorts` does: `SmtPrinter::input` only fires the first time a node is
+    // seen, so a query that reuses a cached input would otherwise vanish
+    // from the `Assignment` `parse_assignment` builds.
+    inputs: Mutex<Vec<SymbolId>>,
+    width: u32,
 }
 
+const DEFAULT_BITVECTOR_WIDTH: u32 = 64;
+
 impl ExternalSolver {
     pub fn new<P>(path: P) -> Result<Self, SolverError>
     where
@@ -26,7 +53,63 @@ impl ExternalSolver {
 
         let output = Arc::new(Mutex::new(writer));
 
-        Ok(Self { output })
+        Ok(Self {
+            output,
+            response: None,
+            _process: None,
+            visited: Mutex::new(HashMap::new()),
+            sorts: Mutex::new(HashMap::new()),
+            inputs: Mutex::new(Vec::new()),
+            width: DEFAULT_BITVECTOR_WIDTH,
+        })
+    }
+
+    /// Overrides the bitvector width used for `(_ BitVec N)` declarations
+    /// (defaults to [`DEFAULT_BITVECTOR_WIDTH`]).
+    pub fn with_width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Spawns `program` (e.g. `boolector`, `z3`, `cvc5`) as a long-running child
+    /// process, feeding it the generated `QF_BV` script on stdin and reading its
+    /// `(check-sat)`/`(get-model)` replies back from stdout.
+    pub fn with_command<S, I, A>(program: S, args: I) -> Result<Self, SolverError>
+    where
+        S: AsRef<OsStr>,
+        I: IntoIterator<Item = A>,
+        A: AsRef<OsStr>,
+    {
+        let mut process = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdin: ChildStdin = process
+            .stdin
+            .take()
+            .expect("child spawned with piped stdin");
+        let stdout: ChildStdout = process
+            .stdout
+            .take()
+            .expect("child spawned with piped stdout");
+
+        let mut writer = stdin;
+
+        write_init(&mut writer)?;
+
+        Ok(Self {
+            output: Arc::new(Mutex::new(writer)),
+            response: Some(Arc::new(Mutex::new(BufReader::new(
+                Box::new(stdout) as Box<dyn Read + Send>
+            )))),
+            _process: Some(process),
+            visited: Mutex::new(HashMap::new()),
+            sorts: Mutex::new(HashMap::new()),
+            inputs: Mutex::new(Vec::new()),
+            width: DEFAULT_BITVECTOR_WIDTH,
+        })
     }
 }
 
@@ -42,6 +125,12 @@ impl Default for ExternalSolver {
 
         Self {
             output: Arc::new(Mutex::new(file)),
+            response: None,
+            _process: None,
+            visited: Mutex::new(HashMap::new()),
+            sorts: Mutex::new(HashMap::new()),
+            inputs: Mutex::new(Vec::new()),
+            width: DEFAULT_BITVECTOR_WIDTH,
         }
     }
 }
@@ -52,50 +141,288 @@ impl Solver for ExternalSolver {
     }
 
     fn solve_impl<F: Formula>(&self, formula: &F) -> Result<Option<Assignment>, SolverError> {
+        let mut printer = SmtPrinter {
+            buffer: String::new(),
+            inputs: &self.inputs,
+            sorts: &self.sorts,
+            width: self.width,
+        };
+
+        let root = formula.root();
+
+        // Seeded from the persistent cache: nodes this query shares with an
+        // earlier one are skipped by `traverse`, so only the newly reachable
+        // nodes end up declared/asserted here.
+        {
+            let mut visited = self.visited.lock().expect("no other thread should fail");
+
+            formula.traverse(root, &mut visited, &mut printer)?;
+        }
+
+        let query = printer.as_bool_term(root);
+
         {
             let mut output = self.output.lock().expect("no other thread should fail");
 
-            writeln!(output, "(push 1)")?;
+            // Declarations/assertions describing the formula DAG are
+            // permanent: once a node is declared, it stays declared for the
+            // lifetime of the solver process, so they are written outside
+            // any push/pop scope and safely reused by later queries that
+            // share this subformula. Only "this path is taken" is
+            // query-specific, so that is the one thing scoped by push/pop,
+            // retracted again before the next, possibly divergent, query.
+            output.write_all(printer.buffer.as_bytes())?;
+            writeln!(
+                output,
+                "(push 1)\n(assert {})\n(check-sat)\n(get-model)\n(pop 1)",
+                query
+            )?;
+            output.flush()?;
+        }
+
+        let response = match &self.response {
+            Some(response) => response,
+            // no solver process attached (writing to a file or to stdout): we
+            // cannot decide satisfiability ourselves.
+            None => return Err(SolverError::SatUnknown),
+        };
+
+        let mut reader = response.lock().expect("no other thread should fail");
 
-            // give lock back here
+        match read_sat_result(&mut *reader)? {
+            SatResult::Unsat => Ok(None),
+            SatResult::Unknown => Err(SolverError::SatUnknown),
+            SatResult::Sat => {
+                let model = read_model(&mut *reader)?;
+                let inputs = self.inputs.lock().expect("no other thread should fail");
+
+                Ok(Some(parse_assignment(&inputs, &model)))
+            }
         }
+    }
+}
 
-        let mut printer = SmtPrinter {
-            output: self.output.clone(),
+#[derive(Debug, PartialEq, Eq)]
+enum SatResult {
+    Sat,
+    Unsat,
+    Unknown,
+}
+
+fn unexpected_eof() -> SolverError {
+    SolverError::from(std::io::Error::from(ErrorKind::UnexpectedEof))
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, SolverError> {
+    let mut line = String::new();
+
+    let n = reader.read_line(&mut line).map_err(SolverError::from)?;
+
+    if n == 0 {
+        return Err(unexpected_eof());
+    }
+
+    Ok(line)
+}
+
+fn read_sat_result<R: BufRead>(reader: &mut R) -> Result<SatResult, SolverError> {
+    loop {
+        let line = read_line(reader)?;
+
+        match line.split_whitespace().next() {
+            Some("sat") => return Ok(SatResult::Sat),
+            Some("unsat") => return Ok(SatResult::Unsat),
+            Some("unknown") => return Ok(SatResult::Unknown),
+            // blank lines or solver banners: keep reading
+            _ => continue,
+        }
+    }
+}
+
+/// Reads the full `(get-model)` response, which is itself a parenthesized
+/// s-expression, by tracking paren depth until it returns to zero.
+fn read_model<R: BufRead>(reader: &mut R) -> Result<String, SolverError> {
+    let mut depth = 0i32;
+    let mut started = false;
+    let mut model = String::new();
+
+    loop {
+        let line = read_line(reader)?;
+
+        for c in line.chars() {
+            match c {
+                '(' => {
+                    depth += 1;
+                    started = true;
+                }
+                ')' => depth -= 1,
+                _ => {}
+            }
+        }
+
+        model.push_str(&line);
+
+        if started && depth <= 0 {
+            return Ok(model);
+        }
+    }
+}
+
+/// Parses `(define-fun x7 () (_ BitVec 64) #x000000000000002a)` entries (also
+/// accepting `(_ bvNNN 64)` and `#b...` literals) and maps each `x{idx}` back
+/// onto the `SymbolId`s that `SmtPrinter::input` declared, keeping only the
+/// input symbols (`inputs`) in the resulting `Assignment`.
+fn parse_assignment(inputs: &[SymbolId], model: &str) -> Assignment {
+    let mut values = HashMap::<SymbolId, BitVector>::new();
+
+    for define in model.split("(define-fun ").skip(1) {
+        let name = match define.split_whitespace().next() {
+            Some(name) => name,
+            None => continue,
         };
-        let mut visited = HashMap::<SymbolId, Result<SymbolId, SolverError>>::new();
 
-        formula.traverse(formula.root(), &mut visited, &mut printer)?;
+        let idx: SymbolId = match name.strip_prefix('x').and_then(|n| n.parse().ok()) {
+            Some(idx) => idx,
+            None => continue,
+        };
 
-        let mut output = self.output.lock().expect("no other thread should fail");
+        if let Some(value) = parse_bitvector_literal(define) {
+            values.insert(idx, value);
+        }
+    }
 
-        writeln!(output, "(check-sat)\n(get-model)\n(pop 1)")?;
+    inputs
+        .iter()
+        .filter_map(|idx| values.get(idx).map(|v| (*idx, *v)))
+        .collect::<HashMap<_, _>>()
+        .into()
+}
 
-        Err(SolverError::SatUnknown)
+fn parse_bitvector_literal(define: &str) -> Option<BitVector> {
+    // Skip past `<name> () (_ BitVec <width>)` to the value that follows it,
+    // rather than searching from the end: the value is itself followed by
+    // the `define-fun`'s closing paren and then whatever comes after it in
+    // the full model (more defines, or the model's own closing parens), so
+    // anchoring on the sort we just declared is the only fixed point here.
+    let sort_start = define.find("(_ BitVec")?;
+    let sort_close = sort_start + define[sort_start..].find(')')?;
+    let rest = define[sort_close + 1..].trim_start();
+
+    // `(_ bvNNN 64)`
+    if let Some(rest) = rest.strip_prefix("(_ bv") {
+        return rest
+            .split(|c: char| c == ' ' || c == ')')
+            .next()
+            .and_then(|n| n.parse().ok())
+            .map(BitVector);
     }
+
+    let value = rest.split(|c: char| c == ')' || c.is_whitespace()).next()?;
+
+    if let Some(hex) = value.strip_prefix("#x") {
+        return u64::from_str_radix(hex, 16).ok().map(BitVector);
+    }
+
+    if let Some(bin) = value.strip_prefix("#b") {
+        return u64::from_str_radix(bin, 2).ok().map(BitVector);
+    }
+
+    None
 }
 
-struct SmtPrinter {
-    output: Arc<Mutex<dyn Write>>,
+/// The SMT-LIB2 sort assigned to a declared node: either a bitvector of some
+/// width or `Bool`, as produced by the comparison operators and `Not`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SmtSort {
+    Bool,
+    BitVec(u32),
 }
 
-impl FormulaVisitor<Result<SymbolId, SolverError>> for SmtPrinter {
+impl SmtSort {
+    fn decl(self) -> String {
+        match self {
+            SmtSort::Bool => "Bool".to_string(),
+            SmtSort::BitVec(width) => format!("(_ BitVec {})", width),
+        }
+    }
+}
+
+/// Accumulates the SMT-LIB2 text for an entire `formula.traverse` walk in a
+/// local buffer instead of locking `output` once per node; the caller flushes
+/// the buffer to the shared writer in a single lock acquisition once the walk
+/// is done.
+struct SmtPrinter<'a> {
+    buffer: String,
+    inputs: &'a Mutex<Vec<SymbolId>>,
+    // sort of every node visited so far (including earlier queries), so a
+    // later node can tell whether an operand it refers to is Bool and needs
+    // coercing to a bitvector, even if that operand was cached and skipped
+    // by this call's traversal
+    sorts: &'a Mutex<HashMap<SymbolId, SmtSort>>,
+    width: u32,
+}
+
+impl<'a> SmtPrinter<'a> {
+    fn sort_of(&self, idx: SymbolId) -> Option<SmtSort> {
+        self.sorts
+            .lock()
+            .expect("no other thread should fail")
+            .get(&idx)
+            .copied()
+    }
+
+    fn set_sort(&self, idx: SymbolId, sort: SmtSort) {
+        self.sorts
+            .lock()
+            .expect("no other thread should fail")
+            .insert(idx, sort);
+    }
+
+    /// Returns a term that evaluates `idx` as a bitvector, wrapping it in an
+    /// `ite` if it was declared `Bool` (as `Equals`/`Sltu`/`Not` results are).
+    fn as_bitvec_term(&self, idx: SymbolId) -> String {
+        match self.sort_of(idx) {
+            Some(SmtSort::Bool) => format!(
+                "(ite x{} (_ bv1 {}) (_ bv0 {}))",
+                idx, self.width, self.width
+            ),
+            _ => format!("x{}", idx),
+        }
+    }
+
+    /// Returns a term that evaluates `idx` as a `Bool`, treating a non-zero
+    /// bitvector as true (the usual symbolic-execution convention). Used to
+    /// assert a path condition, which may be rooted at either sort.
+    fn as_bool_term(&self, idx: SymbolId) -> String {
+        match self.sort_of(idx) {
+            Some(SmtSort::Bool) => format!("x{}", idx),
+            _ => format!("(not (= x{} (_ bv0 {})))", idx, self.width),
+        }
+    }
+}
+
+impl<'a> FormulaVisitor<Result<SymbolId, SolverError>> for SmtPrinter<'a> {
     fn input(&mut self, idx: SymbolId, name: &str) -> Result<SymbolId, SolverError> {
-        let mut o = self.output.lock().expect("no other thread should fail");
+        self.buffer.push_str(&format!(
+            "(declare-fun x{} () (_ BitVec {})); {:?}\n",
+            idx, self.width, name
+        ));
+        self.set_sort(idx, SmtSort::BitVec(self.width));
 
-        writeln!(o, "(declare-fun x{} () (_ BitVec 64)); {:?}", idx, name)?;
+        self.inputs
+            .lock()
+            .expect("no other thread should fail")
+            .push(idx);
 
         Ok(idx)
     }
 
     fn constant(&mut self, idx: SymbolId, v: BitVector) -> Result<SymbolId, SolverError> {
-        let mut o = self.output.lock().expect("no other thread should fail");
-
-        writeln!(
-            o,
-            "(declare-fun x{} () (_ BitVec 64))\n(assert (= x{} (_ bv{} 64)))",
-            idx, idx, v.0
-        )?;
+        self.buffer.push_str(&format!(
+            "(declare-fun x{} () (_ BitVec {}))\n(assert (= x{} (_ bv{} {})))\n",
+            idx, self.width, idx, v.0, self.width
+        ));
+        self.set_sort(idx, SmtSort::BitVec(self.width));
 
         Ok(idx)
     }
@@ -106,16 +433,32 @@ impl FormulaVisitor<Result<SymbolId, SolverError>> for SmtPrinter {
         op: BVOperator,
         v: Result<SymbolId, SolverError>,
     ) -> Result<SymbolId, SolverError> {
-        let mut o = self.output.lock().expect("no other thread should fail");
+        let v = v?;
+
+        let sort = match op {
+            BVOperator::Not => {
+                let operand = self.as_bool_term(v);
+                self.buffer.push_str(&format!(
+                    "(declare-fun x{} () Bool)\n(assert (= x{} (not {})))\n",
+                    idx, idx, operand
+                ));
+                SmtSort::Bool
+            }
+            _ => {
+                let operand = self.as_bitvec_term(v);
+                self.buffer.push_str(&format!(
+                    "(declare-fun x{} () (_ BitVec {}))\n(assert (= x{} ({} {})))\n",
+                    idx,
+                    self.width,
+                    idx,
+                    to_smt(op),
+                    operand
+                ));
+                SmtSort::BitVec(self.width)
+            }
+        };
 
-        writeln!(
-            o,
-            "(declare-fun x{} () (_ BitVec 64))\n(assert (= x{} ({} x{})))",
-            idx,
-            idx,
-            to_smt(op),
-            v?
-        )?;
+        self.set_sort(idx, sort);
 
         Ok(idx)
     }
@@ -127,17 +470,24 @@ impl FormulaVisitor<Result<SymbolId, SolverError>> for SmtPrinter {
         lhs: Result<SymbolId, SolverError>,
         rhs: Result<SymbolId, SolverError>,
     ) -> Result<SymbolId, SolverError> {
-        let mut o = self.output.lock().expect("no other thread should fail");
+        let lhs = self.as_bitvec_term(lhs?);
+        let rhs = self.as_bitvec_term(rhs?);
 
-        writeln!(
-            o,
-            "(declare-fun x{} () (_ BitVec 64))\n(assert (= x{} ({} x{} x{})))",
+        let sort = match op {
+            BVOperator::Equals | BVOperator::Sltu => SmtSort::Bool,
+            _ => SmtSort::BitVec(self.width),
+        };
+
+        self.buffer.push_str(&format!(
+            "(declare-fun x{} () {})\n(assert (= x{} ({} {} {})))\n",
             idx,
+            sort.decl(),
             idx,
             to_smt(op),
-            lhs?,
-            rhs?
-        )?;
+            lhs,
+            rhs
+        ));
+        self.set_sort(idx, sort);
 
         Ok(idx)
     }
@@ -156,3 +506,215 @@ fn to_smt(op: BVOperator) -> &'static str {
         BVOperator::Sltu => "bvult",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // `Formula`/`FormulaVisitor` are implemented by the symbolic execution
+    // engine, which this crate doesn't have direct access to here, so this
+    // drives the exact sequence `solve_impl` relies on (skip re-declaring a
+    // node already in `visited`, write permanent declarations before
+    // `(push 1)`, scope only the query assertion) by hand instead of through
+    // a fake `Formula`. Regression test for 695a6a5, which had to fix this
+    // same sequencing once already.
+    #[test]
+    fn test_shared_declaration_survives_pop_across_queries() {
+        let visited: Mutex<HashMap<SymbolId, Result<SymbolId, SolverError>>> =
+            Mutex::new(HashMap::new());
+        let inputs = Mutex::new(Vec::new());
+        let sorts = Mutex::new(HashMap::new());
+        let mut output = Vec::<u8>::new();
+
+        for _ in 0..2 {
+            let mut printer = SmtPrinter {
+                buffer: String::new(),
+                inputs: &inputs,
+                sorts: &sorts,
+                width: 64,
+            };
+
+            let root = 1;
+
+            {
+                let mut visited = visited.lock().expect("no other thread should fail");
+                if !visited.contains_key(&root) {
+                    let result = printer.input(root, "x");
+                    visited.insert(root, result);
+                }
+            }
+
+            let query = printer.as_bool_term(root);
+
+            output.write_all(printer.buffer.as_bytes()).unwrap();
+            write!(
+                output,
+                "(push 1)\n(assert {})\n(check-sat)\n(get-model)\n(pop 1)\n",
+                query
+            )
+            .unwrap();
+        }
+
+        let script = String::from_utf8(output).unwrap();
+
+        assert_eq!(
+            script.matches("declare-fun x1").count(),
+            1,
+            "a node already in `visited` must not be re-declared by a later query"
+        );
+
+        let first_push = script.find("(push 1)").unwrap();
+        assert!(
+            script[..first_push].contains("declare-fun x1"),
+            "the shared declaration must sit outside any push/pop scope"
+        );
+        assert_eq!(script.matches("(push 1)").count(), 2);
+        assert_eq!(script.matches("(pop 1)").count(), 2);
+    }
+
+    #[test]
+    fn test_parse_bitvector_literal_hex() {
+        let define = "x7 () (_ BitVec 64) #x000000000000002a)";
+
+        assert_eq!(parse_bitvector_literal(define), Some(BitVector(42)));
+    }
+
+    #[test]
+    fn test_parse_bitvector_literal_bin() {
+        let define = "x3 () (_ BitVec 8) #b00101010)";
+
+        assert_eq!(parse_bitvector_literal(define), Some(BitVector(42)));
+    }
+
+    #[test]
+    fn test_parse_bitvector_literal_bv_form() {
+        let define = "x9 () (_ BitVec 64) (_ bv42 64))";
+
+        assert_eq!(parse_bitvector_literal(define), Some(BitVector(42)));
+    }
+
+    #[test]
+    fn test_parse_bitvector_literal_rejects_garbage() {
+        assert_eq!(parse_bitvector_literal("x1 () (_ BitVec 64) true)"), None);
+    }
+
+    #[test]
+    fn test_parse_assignment_keeps_only_input_symbols() {
+        let model = "(model \n\
+            (define-fun x1 () (_ BitVec 64) #x000000000000002a)\n\
+            (define-fun x2 () (_ BitVec 64) #x0000000000000000)\n\
+            )";
+
+        let assignment = parse_assignment(&[1], model);
+
+        assert_eq!(assignment.0.get(&1), Some(&BitVector(42)));
+        assert_eq!(assignment.0.get(&2), None);
+    }
+
+    #[test]
+    fn test_parse_assignment_skips_inputs_missing_from_model() {
+        let model = "(model (define-fun x1 () (_ BitVec 64) #x000000000000002a))";
+
+        let assignment = parse_assignment(&[1, 5], model);
+
+        assert_eq!(assignment.0.len(), 1);
+        assert_eq!(assignment.0.get(&5), None);
+    }
+
+    #[test]
+    fn test_read_sat_result_sat() {
+        let mut reader = Cursor::new(b"some-solver-banner\n\nsat\n".to_vec());
+
+        assert_eq!(read_sat_result(&mut reader).unwrap(), SatResult::Sat);
+    }
+
+    #[test]
+    fn test_read_sat_result_unsat() {
+        let mut reader = Cursor::new(b"unsat\n".to_vec());
+
+        assert_eq!(read_sat_result(&mut reader).unwrap(), SatResult::Unsat);
+    }
+
+    #[test]
+    fn test_read_sat_result_unknown() {
+        let mut reader = Cursor::new(b"unknown\n".to_vec());
+
+        assert_eq!(read_sat_result(&mut reader).unwrap(), SatResult::Unknown);
+    }
+
+    #[test]
+    fn test_read_sat_result_unexpected_eof() {
+        let mut reader = Cursor::new(b"".to_vec());
+
+        assert!(read_sat_result(&mut reader).is_err());
+    }
+
+    #[test]
+    fn test_read_model_tracks_nested_parens() {
+        let mut reader = Cursor::new(
+            b"(model\n  (define-fun x1 () (_ BitVec 64) #x000000000000002a)\n)\n".to_vec(),
+        );
+
+        let model = read_model(&mut reader).unwrap();
+
+        assert!(model.trim_end().ends_with(')'));
+        assert!(model.contains("define-fun x1"));
+    }
+
+    #[test]
+    fn test_sort_tagging_persists_across_lookups() {
+        let inputs = Mutex::new(Vec::new());
+        let sorts = Mutex::new(HashMap::new());
+        let printer = SmtPrinter {
+            buffer: String::new(),
+            inputs: &inputs,
+            sorts: &sorts,
+            width: 64,
+        };
+
+        assert_eq!(printer.sort_of(1), None);
+
+        printer.set_sort(1, SmtSort::Bool);
+        printer.set_sort(2, SmtSort::BitVec(64));
+
+        assert_eq!(printer.sort_of(1), Some(SmtSort::Bool));
+        assert_eq!(printer.sort_of(2), Some(SmtSort::BitVec(64)));
+    }
+
+    #[test]
+    fn test_as_bitvec_term_coerces_bool_node() {
+        let inputs = Mutex::new(Vec::new());
+        let sorts = Mutex::new(HashMap::new());
+        let printer = SmtPrinter {
+            buffer: String::new(),
+            inputs: &inputs,
+            sorts: &sorts,
+            width: 64,
+        };
+
+        printer.set_sort(1, SmtSort::Bool);
+        printer.set_sort(2, SmtSort::BitVec(64));
+
+        assert_eq!(printer.as_bitvec_term(1), "(ite x1 (_ bv1 64) (_ bv0 64))");
+        assert_eq!(printer.as_bitvec_term(2), "x2");
+    }
+
+    #[test]
+    fn test_as_bool_term_coerces_bitvec_node() {
+        let inputs = Mutex::new(Vec::new());
+        let sorts = Mutex::new(HashMap::new());
+        let printer = SmtPrinter {
+            buffer: String::new(),
+            inputs: &inputs,
+            sorts: &sorts,
+            width: 64,
+        };
+
+        printer.set_sort(1, SmtSort::Bool);
+        printer.set_sort(2, SmtSort::BitVec(64));
+
+        assert_eq!(printer.as_bool_term(1), "x1");
+        assert_eq!(printer.as_bool_term(2), "(not (= x2 (_ bv0 64)))");
+    }
+}